@@ -1,9 +1,55 @@
 //! Analysis of pseudorandom streams.
 
-use std::cmp;
 use std::collections::HashSet;
 
-use Random;
+use {Random, Seedable};
+
+/// Approximate the Gauss error function.
+///
+/// This uses the Abramowitz & Stegun 7.1.26 rational approximation, which is more than accurate
+/// enough for turning a statistic into a score.
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let y = 1.0
+        - (((((1.061405429 * t - 1.453152027) * t) + 1.421413741) * t - 0.284496736) * t
+            + 0.254829592)
+            * t
+            * (-x * x).exp();
+    if x < 0.0 {
+        -y
+    } else {
+        y
+    }
+}
+
+/// The upper tail of the standard normal distribution, i.e. `P(Z > z)`.
+fn normal_tail(z: f64) -> f64 {
+    0.5 * (1.0 - erf(z / ::std::f64::consts::SQRT_2))
+}
+
+/// Score a chi-squared goodness-of-fit statistic.
+///
+/// `df` is assumed large, so the statistic is turned into a standard-normal `z` through the
+/// Wilson–Hilferty approximation and then into a p-value via the normal tail. The score is high
+/// when the p-value sits in the healthy central band (roughly 0.01–0.99) and drops sharply toward
+/// either tail: a p-value near zero means the stream is non-uniform, near one means it is
+/// suspiciously uniform.
+fn chi_squared_score(chi2: f64, df: f64) -> u8 {
+    // A degenerate statistic has nothing to say about the stream: an empty stream gives
+    // `chi2 = NaN` and a matrix with no usable pairs gives `df = 0`. Treat these as the worst
+    // possible score rather than letting `NaN.min(1.0) == 1.0` pin the score to the maximum.
+    if df <= 0.0 || !chi2.is_finite() {
+        return 0;
+    }
+    let z = ((chi2 / df).powf(1.0 / 3.0) - (1.0 - 2.0 / (9.0 * df)))
+        / (2.0 / (9.0 * df)).sqrt();
+    let p = normal_tail(z);
+    if p.is_nan() {
+        return 0;
+    }
+    let tail = if p < 0.5 { p } else { 1.0 - p };
+    (255.0 * (tail / 0.01).min(1.0)) as u8
+}
 
 /// A analysis report extracted from some stream.
 pub struct Report {
@@ -15,10 +61,17 @@ pub struct Report {
     collisions: u8,
     /// The bit dependency matrix.
     ///
-    /// This contains the probability that bit `x` is set if bit `y` is, i.e. `p(y|x)`.
+    /// Entry `[x][y]` counts the samples in which bit `x` and bit `y` are both set. The diagonal
+    /// `[x][x]` therefore holds the marginal count of samples with bit `x` set, which the scoring
+    /// uses to build a per-pair contingency table.
     dependency_matrix: [[u32; 64]; 64],
     /// The distribution of the sample, modulo 4096.
     distribution: [u16; 4096],
+    /// The number of samples actually analysed from the stream.
+    ///
+    /// This can be lower than the requested sample size if the source ran dry early. The first
+    /// number drawn, which seeds the cycle detection, is consumed but not counted.
+    samples: u32,
 }
 
 impl Default for Report {
@@ -28,6 +81,7 @@ impl Default for Report {
             collisions: 0,
             dependency_matrix: [[0; 64]; 64],
             distribution: [0; 4096],
+            samples: 0,
         }
     }
 }
@@ -38,118 +92,152 @@ impl Report {
         let mut report = Report::default();
         let mut set = HashSet::new();
 
-        let start = rand.get_random();
+        // Seed the cycle detection with the first number. If the stream is empty, there is nothing
+        // to analyse.
+        let start = match rand.try_get_random() {
+            Ok(r) => r,
+            Err(_) => return report,
+        };
         for n in 0..1 << 16 {
-            // Collect a random number.
-            let r = rand.get_random();
+            // Collect a random number. If the source runs dry, stop with what we have gathered so
+            // far rather than fabricating zeros.
+            let r = match rand.try_get_random() {
+                Ok(r) => r,
+                Err(_) => break,
+            };
+            report.samples += 1;
 
-            // Update the bit depedency matrix.
+            // Update the bit depedency matrix with the bits that co-occur in this sample.
             for x in 0..64 {
                 for y in 0..64 {
-                    report.dependency_matrix[x][y] += ((r & (1 << x) == 0) <= (r & (1 << y) == 0)) as u32;
+                    report.dependency_matrix[x][y] +=
+                        (r & (1 << x) != 0 && r & (1 << y) != 0) as u32;
                 }
             }
 
-            // Increment the distribution entry.
-            report.distribution[r as usize % 4096] += 1;
+            // Increment the distribution entry, saturating so a transform that collapses the
+            // stream onto a few buckets still reads as strongly non-uniform rather than wrapping.
+            let bucket = r as usize % 4096;
+            report.distribution[bucket] = report.distribution[bucket].saturating_add(1);
 
             // If it returned to the first number, set the cycle length.
             if report.cycle_length == 0 && r == start {
                 report.cycle_length = n;
             }
 
-            // Insert the random number into the set and update the collision number.
-            report.collisions += (!set.insert(r)) as u8;
+            // Insert the random number into the set and update the collision number. A weakening
+            // transform can collapse the stream onto very few values, so this saturates rather
+            // than overflowing; the score only cares whether there were zero, one or more.
+            report.collisions = report.collisions.saturating_add((!set.insert(r)) as u8);
         }
 
         report
     }
 
+    /// The number of samples this report was built from.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
     /// Get the final score of this report.
     pub fn get_score(&self) -> Score {
+        // A score only means something once enough of the stream has been seen. A short file or
+        // pipe trivially shows no cycle and no collision, and — worse — its chi-squared statistics
+        // degenerate: a sparse distribution leaves ~4095 empty buckets each contributing `E` to the
+        // sum, so `chi2 ≈ df` and the goodness-of-fit test reads as a perfect fit regardless of
+        // quality. Below this threshold none of the metrics can earn marks, so an under-sampled
+        // source is not rated above a full-length good stream.
+        const MIN_SAMPLES: u32 = 1 << 12;
+        let sampled_enough = self.samples >= MIN_SAMPLES;
+
         Score {
             // The cycle should not be less than the sample size.
-            cycle: if self.cycle_length == 0 {
+            cycle: if sampled_enough && self.cycle_length == 0 {
                 255
             } else {
                 0
             },
             // Ideally, there should be no collisions in our sample. Applying the birthday problem
             // still gives us very small probability of such a collision occuring.
-            collision: match self.collisions {
-                0 => 255,
-                1 => 20,
-                _ => 0,
+            collision: if !sampled_enough {
+                0
+            } else {
+                match self.collisions {
+                    0 => 255,
+                    1 => 20,
+                    _ => 0,
+                }
             },
-            bit_dependency: {
-                // Calculate the minimum and maximum entry of the dependency matrix.
-                let mut max = 0;
-                let mut min = !0;
+            bit_dependency: if !sampled_enough {
+                0
+            } else {
+                // Chi-squared test of independence for every distinct bit pair. Each pair forms a
+                // 2x2 contingency table from the joint count `[x][y]` and the marginals on the
+                // diagonal; its Pearson statistic is a well-calibrated chi-squared with one degree
+                // of freedom, summed over all pairs. A pair with a degenerate marginal (a bit that
+                // is always set or always clear) has no table and is skipped.
+                //
+                // This deliberately diverges from the naive `E = N / 4` co-occurrence count, which
+                // only holds for unbiased bits: computing the expected cells from the observed
+                // marginals keeps the statistic calibrated even when individual bits are skewed.
+                // This metric carries `df = number of pairs` rather than the distribution metric's
+                // 4095; both statistics are scored against their own `df`, so the two scores sit on
+                // the same 0–255 scale in `total()`.
+                let n = self.samples as f64;
+                let mut chi2 = 0.0;
+                let mut terms = 0u32;
                 for x in 0..64 {
-                    for y in 0..64 {
-                        max = cmp::max(self.dependency_matrix[x][y], max);
-                        min = cmp::min(self.dependency_matrix[x][y], min);
+                    for y in (x + 1)..64 {
+                        let both = self.dependency_matrix[x][y] as f64;
+                        let x_set = self.dependency_matrix[x][x] as f64;
+                        let y_set = self.dependency_matrix[y][y] as f64;
+
+                        // Expected cell counts under independence of the two bits.
+                        let e11 = x_set * y_set / n;
+                        let e10 = x_set * (n - y_set) / n;
+                        let e01 = (n - x_set) * y_set / n;
+                        let e00 = (n - x_set) * (n - y_set) / n;
+                        if e11 <= 0.0 || e10 <= 0.0 || e01 <= 0.0 || e00 <= 0.0 {
+                            continue;
+                        }
+
+                        // Observed cell counts.
+                        let o10 = x_set - both;
+                        let o01 = y_set - both;
+                        let o00 = n - x_set - y_set + both;
+
+                        chi2 += (both - e11) * (both - e11) / e11
+                            + (o10 - e10) * (o10 - e10) / e10
+                            + (o01 - e01) * (o01 - e01) / e01
+                            + (o00 - e00) * (o00 - e00) / e00;
+                        terms += 1;
                     }
                 }
 
-                // Rate it based on it's distance to the ideal value.
-                let pmin = match 65536 - min as i32 {
-                    0...4 => 127,
-                    4...6 => 126,
-                    6...16 => 120,
-                    16...32 => 90,
-                    32...64 => 50,
-                    64...80 => 20,
-                    _ => 0,
-                };
-
-                // Rate it based on it's distance to the ideal value.
-                let pmax = match min as i32 - 65536 {
-                    0...4 => 128,
-                    4...6 => 126,
-                    6...16 => 120,
-                    16...32 => 90,
-                    32...64 => 50,
-                    64...80 => 20,
-                    _ => 0,
-                };
-
-                pmin + pmax
+                chi_squared_score(chi2, terms as f64)
             },
-            distribution: {
-                // Calculate the minimum and maximum entry of the distribution array.
-                let mut max = 0;
-                let mut min = !0;
-                for i in 0..4096 {
-                    max = cmp::max(self.distribution[i], max);
-                    min = cmp::min(self.distribution[i], min);
-                }
+            distribution: if !sampled_enough {
+                0
+            } else {
+                // Chi-squared goodness-of-fit against a uniform distribution over the 4096 buckets.
+                // Each bucket is expected to hold `N / 4096` of the samples.
+                let expected = self.samples as f64 / 4096.0;
 
-                // Rate it based on it's distance to the ideal value.
-                let pmin = match 32 - min as i32 {
-                    0...4 => 127,
-                    4...6 => 126,
-                    6...10 => 110,
-                    10...15 => 70,
-                    15...18 => 50,
-                    18...20 => 30,
-                    20...32 => 20,
-                    _ => 0,
-                };
-
-                // Rate it based on it's distance to the ideal value.
-                let pmax = match min as i32 - 32 {
-                    0...4 => 128,
-                    4...6 => 126,
-                    6...10 => 110,
-                    10...15 => 70,
-                    15...18 => 50,
-                    18...20 => 30,
-                    20...32 => 20,
-                    _ => 0,
-                };
+                // The chi-squared approximation is only trustworthy when the expected per-bucket
+                // count is at least 5. Below that a sparse, distinct-valued stream degenerates —
+                // each empty bucket contributes `E` so `chi2 ≈ df` — and the fit would read as
+                // perfect regardless of quality, so the statistic is not valid.
+                if expected < 5.0 {
+                    0
+                } else {
+                    let mut chi2 = 0.0;
+                    for i in 0..4096 {
+                        let diff = self.distribution[i] as f64 - expected;
+                        chi2 += diff * diff / expected;
+                    }
 
-                pmin + pmax
+                    chi_squared_score(chi2, (4096 - 1) as f64)
+                }
             },
         }
     }
@@ -168,11 +256,134 @@ pub struct Score {
 }
 
 impl Score {
+    /// The quality of the cycle length.
+    pub fn cycle(&self) -> u8 {
+        self.cycle
+    }
+
+    /// The quality of occurence of collisions.
+    pub fn collision(&self) -> u8 {
+        self.collision
+    }
+
+    /// The quality of the bit dependency matrix.
+    pub fn bit_dependency(&self) -> u8 {
+        self.bit_dependency
+    }
+
+    /// The quality of the distribution.
+    pub fn distribution(&self) -> u8 {
+        self.distribution
+    }
+
     /// Sum the scores together to a single integer.
-    pub fn total(self) -> u16 {
+    pub fn total(&self) -> u16 {
         self.cycle as u16
             + self.collision as u16
             + self.bit_dependency as u16
             + self.distribution as u16
     }
 }
+
+/// A report on how sensitive a generator is to its seed.
+///
+/// It reseeds the generator with many pairs of seeds differing by a single bit and compares the
+/// first handful of outputs of each stream. A strong generator exhibits the strict avalanche
+/// criterion — a one-bit seed change flips about half the output bits — and never produces the
+/// same output at the same position from two nearby seeds.
+pub struct SeedReport {
+    /// The average Hamming distance between corresponding outputs of single-bit-apart seeds.
+    avalanche: f64,
+    /// The number of positions at which two nearby streams produced an identical output.
+    collisions: u32,
+    /// The number of output pairs compared.
+    comparisons: u32,
+}
+
+impl SeedReport {
+    /// Investigate a generator's seed sensitivity and create a report.
+    pub fn new<R: Seedable>(mut rand: R) -> SeedReport {
+        // The number of outputs drawn from each stream.
+        const OUTPUTS: usize = 64;
+        // A spread of base seeds, each probed against all of its single-bit neighbours.
+        const BASES: [u64; 8] = [
+            0x0000_0000_0000_0000,
+            0x0000_0000_0000_0001,
+            0x5555_5555_5555_5555,
+            0xAAAA_AAAA_AAAA_AAAA,
+            0xFFFF_FFFF_FFFF_FFFF,
+            0x0123_4567_89AB_CDEF,
+            0xDEAD_BEEF_CAFE_BABE,
+            0x8000_0000_0000_0000,
+        ];
+
+        let mut total_hamming = 0u64;
+        let mut comparisons = 0u32;
+        let mut collisions = 0u32;
+
+        for &base in BASES.iter() {
+            for bit in 0..64 {
+                // Draw the reference stream.
+                rand.reseed(base);
+                let mut reference = [0u64; OUTPUTS];
+                for slot in reference.iter_mut() {
+                    *slot = rand.get_random();
+                }
+
+                // Draw the stream from the single-bit-flipped seed and compare it position-wise.
+                rand.reseed(base ^ (1 << bit));
+                for &r in reference.iter() {
+                    let s = rand.get_random();
+                    total_hamming += (r ^ s).count_ones() as u64;
+                    comparisons += 1;
+                    collisions += (r == s) as u32;
+                }
+            }
+        }
+
+        SeedReport {
+            avalanche: total_hamming as f64 / comparisons as f64,
+            collisions,
+            comparisons,
+        }
+    }
+
+    /// The average number of output bits flipped by a single-bit seed change.
+    ///
+    /// The strict avalanche ideal is 32 of the 64 bits.
+    pub fn avalanche(&self) -> f64 {
+        self.avalanche
+    }
+
+    /// Get the final score of this report.
+    pub fn get_score(&self) -> SeedScore {
+        SeedScore {
+            // Reward a Hamming distance close to the ideal of 32, falling off to zero as it drifts
+            // to either extreme (no flips, or every bit flipped).
+            avalanche: {
+                let deviation = (self.avalanche - 32.0).abs();
+                (255.0 * (1.0 - deviation / 32.0).max(0.0)) as u8
+            },
+            // Reward the absence of cross-stream matches.
+            collision: {
+                let fraction = self.collisions as f64 / self.comparisons as f64;
+                (255.0 * (1.0 - fraction).max(0.0)) as u8
+            },
+        }
+    }
+}
+
+/// The score of a seed-sensitivity report.
+pub struct SeedScore {
+    /// The quality of the avalanche effect.
+    avalanche: u8,
+    /// The quality of cross-stream decorrelation.
+    collision: u8,
+}
+
+impl SeedScore {
+    /// Sum the scores together to a single integer.
+    pub fn total(&self) -> u16 {
+        self.avalanche as u16 + self.collision as u16
+    }
+}
@@ -0,0 +1,68 @@
+//! Interoperability with the `rand` ecosystem.
+//!
+//! Almost every generator a user would want to crush (ChaCha, Pcg, Hc128, StdRng, OsRng, …)
+//! implements `rand_core::RngCore` rather than this crate's `Random`. These adapters bridge the
+//! two, so `crush` becomes a drop-in test harness for the whole ecosystem.
+
+use rand::{RngCore, SeedableRng};
+
+use {Random, Seedable};
+
+/// Adapts any `rand_core::RngCore` into a `Random`.
+///
+/// Each number is produced by a single `next_u64` call.
+pub struct RandCompat<T>(pub T);
+
+impl<T: RngCore> Random for RandCompat<T> {
+    fn get_random(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+}
+
+impl<T: Clone> Clone for RandCompat<T> {
+    fn clone(&self) -> RandCompat<T> {
+        RandCompat(self.0.clone())
+    }
+}
+
+/// Adapts a `SeedableRng` into a `Random` that re-seeds a fresh generator whenever it is cloned.
+///
+/// `crush` clones its generator once per transform. For a `SeedableRng` this means every transform
+/// is fed an identical stream reconstructed from the same seed, rather than sharing one generator
+/// whose state the earlier transforms have already advanced.
+pub struct SeededCompat<T> {
+    seed: u64,
+    rng: T,
+}
+
+impl<T: SeedableRng> SeededCompat<T> {
+    /// Seed a fresh generator from a `u64`.
+    pub fn seed_from_u64(seed: u64) -> SeededCompat<T> {
+        SeededCompat {
+            seed,
+            rng: T::seed_from_u64(seed),
+        }
+    }
+}
+
+impl<T: RngCore> Random for SeededCompat<T> {
+    fn get_random(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+}
+
+impl<T: SeedableRng> Clone for SeededCompat<T> {
+    fn clone(&self) -> SeededCompat<T> {
+        SeededCompat {
+            seed: self.seed,
+            rng: T::seed_from_u64(self.seed),
+        }
+    }
+}
+
+impl<T: RngCore + SeedableRng> Seedable for SeededCompat<T> {
+    fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = T::seed_from_u64(seed);
+    }
+}
@@ -9,30 +9,97 @@
 //! In contrast to many other randomness tests, diehardest is stream-aware, making it able to
 //! detect many positional patterns that other tests cannot.
 
-mod transform;
+use std::error;
+use std::fmt;
+use std::io;
+
+#[cfg(feature = "rand")]
+extern crate rand;
+
+pub mod transform;
+mod read;
 pub mod analysis;
+#[cfg(feature = "rand")]
+mod compat;
+
+pub use read::ReadRng;
+pub use transform::{Crusher, CrushReport, Transform};
+#[cfg(feature = "rand")]
+pub use compat::{RandCompat, SeededCompat};
 
 /// A random number generator.
 pub trait Random {
     /// Get a random number.
     fn get_random(&mut self) -> u64;
+
+    /// Try to get a random number.
+    ///
+    /// Unlike `get_random`, this is allowed to fail, letting sources backed by finite or fallible
+    /// streams (files, pipes, sockets) surface end-of-input or I/O failures instead of fabricating
+    /// numbers. The default implementation simply wraps the infallible `get_random`.
+    fn try_get_random(&mut self) -> Result<u64, RandomError> {
+        Ok(self.get_random())
+    }
+}
+
+/// A random number generator that can be reseeded.
+///
+/// This mirrors `rand_core::SeedableRng::seed_from_u64`, collapsing the seed to a single `u64` so
+/// seed-sensitivity can be probed without knowing the generator's native seed type.
+pub trait Seedable: Random {
+    /// Reseed the generator from a `u64`.
+    fn reseed(&mut self, seed: u64);
+}
+
+/// An error occuring while drawing a random number.
+#[derive(Debug)]
+pub enum RandomError {
+    /// The random source was exhausted before a full number could be read.
+    Eof,
+    /// An I/O error occured while reading the random source.
+    Io(io::Error),
+}
+
+impl fmt::Display for RandomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RandomError::Eof => f.write_str("the random source was exhausted"),
+            RandomError::Io(ref err) => write!(f, "I/O error while reading the random source: {}", err),
+        }
+    }
+}
+
+impl error::Error for RandomError {
+    fn description(&self) -> &str {
+        match *self {
+            RandomError::Eof => "the random source was exhausted",
+            RandomError::Io(..) => "I/O error while reading the random source",
+        }
+    }
+}
+
+impl From<io::Error> for RandomError {
+    fn from(err: io::Error) -> RandomError {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            RandomError::Eof
+        } else {
+            RandomError::Io(err)
+        }
+    }
 }
 
 /// Crush this random number generator.
 ///
-/// This rates it based on analysis of itself and transformations of it.
-pub fn crush<R: Random + Clone>(rand: R) -> u32 {
-    analysis::Report::new(rand.clone()).get_score().total() as u32
-        + analysis::Report::new(transform::SkipOne(rand.clone())).get_score().total() as u32
-        + analysis::Report::new(transform::SkipTwo(rand.clone())).get_score().total() as u32
-        + analysis::Report::new(transform::Concatenate32(rand.clone())).get_score().total() as u32
-        + analysis::Report::new(transform::Xor(rand.clone())).get_score().total() as u32
-        + analysis::Report::new(transform::Add(rand.clone())).get_score().total() as u32
-        + analysis::Report::new(transform::Multiply(rand.clone())).get_score().total() as u32
-        + analysis::Report::new(transform::LastBit(rand.clone())).get_score().total() as u32
-        + analysis::Report::new(transform::MultiplyByThree(rand.clone())).get_score().total() as u32
-        + analysis::Report::new(transform::ModularDivideByThree(rand.clone())).get_score().total() as u32
-        + analysis::Report::new(transform::Hamming(rand.clone())).get_score().total() as u32
-        + analysis::Report::new(transform::ParitySkip(rand.clone())).get_score().total() as u32
-        + analysis::Report::new(transform::Rol7(rand.clone())).get_score().total() as u32
+/// This rates it based on analysis of itself and the standard battery of transformations of it.
+/// For control over which transforms run, or a per-transform breakdown, use a `Crusher` directly.
+pub fn crush<R: Random + Clone + 'static>(rand: R) -> u32 {
+    Crusher::standard().crush(rand).total()
+}
+
+/// Crush a seedable random number generator's sensitivity to its seed.
+///
+/// This complements `crush`: where `crush` judges a single stream, this rates how well seeds
+/// differing by a single bit decorrelate, exposing generators whose nearby seeds stay correlated.
+pub fn seed_crush<R: Seedable>(rand: R) -> u32 {
+    analysis::SeedReport::new(rand).get_score().total() as u32
 }
@@ -1,38 +1,13 @@
 extern crate diehardest;
 
-use std::io;
-use std::io::Read;
+use std::io::{self, Cursor, Read};
 
-/// A RNG that reads from the standard input.
-struct StdinRng {
-    stdin: io::Stdin,
-}
-
-impl Clone for StdinRng {
-    fn clone(&self) -> StdinRng {
-        StdinRng {
-            stdin: io::stdin(),
-        }
-    }
-}
-
-impl diehardest::Random for StdinRng {
-    fn get_random(&mut self) -> u64 {
-        let mut buf = [0; 8];
-        self.stdin.read(&mut buf).unwrap();
-
-        let mut x = 0;
-        for &i in &buf {
-            x <<= 8;
-            x |= i as u64;
-        }
-
-        x
-    }
-}
+use diehardest::ReadRng;
 
 fn main() {
-    println!("score: {}", diehardest::crush(StdinRng {
-        stdin: io::stdin(),
-    }));
+    // Slurp the whole input up front so each transform can replay the same bytes.
+    let mut buf = Vec::new();
+    io::stdin().read_to_end(&mut buf).expect("failed to read standard input");
+
+    println!("score: {}", diehardest::crush(ReadRng::new(Cursor::new(buf))));
 }
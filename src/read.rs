@@ -0,0 +1,50 @@
+//! Adapting arbitrary readers into random streams.
+
+use std::io::Read;
+
+use {Random, RandomError};
+
+/// A random number generator reading its numbers from an `io::Read`.
+///
+/// Every number is assembled big-endian from exactly eight bytes of the underlying reader. A short
+/// read or end of input is reported through `try_get_random` rather than silently producing a
+/// truncated number, making it safe to crush a finite file or pipe. The infallible `get_random`
+/// unwraps that result and therefore panics on a short read or end of input; use `try_get_random`
+/// whenever the source may run dry.
+pub struct ReadRng<R> {
+    reader: R,
+}
+
+impl<R: Read> ReadRng<R> {
+    /// Create a reader-backed random number generator.
+    pub fn new(reader: R) -> ReadRng<R> {
+        ReadRng { reader }
+    }
+}
+
+impl<R: Read> Random for ReadRng<R> {
+    fn get_random(&mut self) -> u64 {
+        self.try_get_random().unwrap()
+    }
+
+    fn try_get_random(&mut self) -> Result<u64, RandomError> {
+        let mut buf = [0; 8];
+        self.reader.read_exact(&mut buf)?;
+
+        let mut x = 0;
+        for &i in &buf {
+            x <<= 8;
+            x |= i as u64;
+        }
+
+        Ok(x)
+    }
+}
+
+impl<R: Clone> Clone for ReadRng<R> {
+    fn clone(&self) -> ReadRng<R> {
+        ReadRng {
+            reader: self.reader.clone(),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Stream transformations.
+//!
+//! A transformation wraps a random stream and produces another one that exaggerates the weaknesses
+//! of a poor generator, so the analytical tools in `analysis` have something to bite on. They are
+//! exposed publicly so callers can register their own alongside the built-in ones.
+
+use {analysis, Random, RandomError};
+
+/// A transformation weakening a random stream.
+///
+/// Implementors are themselves `Random` — applying a transform yields another stream — and know
+/// how to wrap an arbitrary inner generator. Because the output is again `Random`, transforms
+/// compose: `Xor<SkipOne<R>>` is a transform of `SkipOne<R>`, and `chain` applies the two in turn
+/// to an `R`.
+pub trait Transform<R: Random>: Random {
+    /// Wrap a random stream in this transform.
+    fn transform(rand: R) -> Self;
+}
+
+/// Chain two transforms, applying `A` and then `B`.
+pub fn chain<R, A, B>(rand: R) -> B
+where
+    R: Random,
+    A: Transform<R>,
+    B: Transform<A>,
+{
+    B::transform(A::transform(rand))
+}
+
+macro_rules! transforms {
+    ($(
+        $(#[$attr:meta])*
+        struct $name:ident($inner:ident) => $body:expr;
+    )*) => {
+        $(
+            $(#[$attr])*
+            #[derive(Clone)]
+            pub struct $name<R>(pub R);
+
+            impl<R: Random> Random for $name<R> {
+                fn get_random(&mut self) -> u64 {
+                    self.try_get_random().unwrap()
+                }
+
+                fn try_get_random(&mut self) -> Result<u64, RandomError> {
+                    let $inner = &mut self.0;
+                    $body
+                }
+            }
+
+            impl<R: Random> Transform<R> for $name<R> {
+                fn transform(rand: R) -> $name<R> {
+                    $name(rand)
+                }
+            }
+        )*
+    };
+}
+
+transforms! {
+    /// Discards one number and returns the next.
+    struct SkipOne(r) => {
+        r.try_get_random()?;
+        r.try_get_random()
+    };
+    /// Discards two numbers and returns the next.
+    struct SkipTwo(r) => {
+        r.try_get_random()?;
+        r.try_get_random()?;
+        r.try_get_random()
+    };
+    /// Concatenates the low halves of two consecutive numbers.
+    struct Concatenate32(r) => {
+        let a = r.try_get_random()? & 0xFFFF_FFFF;
+        let b = r.try_get_random()? & 0xFFFF_FFFF;
+        Ok((a << 32) | b)
+    };
+    /// Exclusive-ors two consecutive numbers.
+    struct Xor(r) => {
+        let a = r.try_get_random()?;
+        let b = r.try_get_random()?;
+        Ok(a ^ b)
+    };
+    /// Adds two consecutive numbers, wrapping around.
+    struct Add(r) => {
+        let a = r.try_get_random()?;
+        let b = r.try_get_random()?;
+        Ok(a.wrapping_add(b))
+    };
+    /// Multiplies two consecutive numbers, wrapping around.
+    struct Multiply(r) => {
+        let a = r.try_get_random()?;
+        let b = r.try_get_random()?;
+        Ok(a.wrapping_mul(b))
+    };
+    /// Assembles a number from the least significant bit of 64 draws.
+    struct LastBit(r) => {
+        let mut x = 0;
+        for _ in 0..64 {
+            x = (x << 1) | (r.try_get_random()? & 1);
+        }
+        Ok(x)
+    };
+    /// Multiplies each number by three, wrapping around.
+    struct MultiplyByThree(r) => Ok(r.try_get_random()?.wrapping_mul(3));
+    /// Multiplies each number by the modular inverse of three, wrapping around.
+    struct ModularDivideByThree(r) => Ok(r.try_get_random()?.wrapping_mul(0xAAAA_AAAA_AAAA_AAAB));
+    /// Returns the Hamming weight of each number.
+    struct Hamming(r) => Ok(r.try_get_random()?.count_ones() as u64);
+    /// Discards each number of odd parity and returns the one after it in its place.
+    struct ParitySkip(r) => {
+        let x = r.try_get_random()?;
+        if x.count_ones() & 1 == 1 {
+            r.try_get_random()
+        } else {
+            Ok(x)
+        }
+    };
+    /// Rotates each number left by seven bits.
+    struct Rol7(r) => Ok(r.try_get_random()?.rotate_left(7));
+}
+
+/// A boxed closure turning a generator into a report.
+type Reporter<R> = Box<dyn Fn(R) -> analysis::Report>;
+
+/// A configurable battery of transforms for crushing a generator.
+///
+/// Where the free `crush` function runs a fixed list, a `Crusher` lets callers pick which
+/// transforms run, register their own, and recover a per-transform breakdown of scores instead of
+/// one opaque sum.
+pub struct Crusher<R> {
+    transforms: Vec<(&'static str, Reporter<R>)>,
+}
+
+impl<R: Random + Clone + 'static> Crusher<R> {
+    /// Create an empty crusher.
+    pub fn new() -> Crusher<R> {
+        Crusher {
+            transforms: Vec::new(),
+        }
+    }
+
+    /// Create a crusher preloaded with the standard battery of transforms.
+    pub fn standard() -> Crusher<R> {
+        let mut crusher = Crusher::new();
+        crusher
+            .register("identity", |rand| analysis::Report::new(rand))
+            .add::<SkipOne<R>>("skip_one")
+            .add::<SkipTwo<R>>("skip_two")
+            .add::<Concatenate32<R>>("concatenate32")
+            .add::<Xor<R>>("xor")
+            .add::<Add<R>>("add")
+            .add::<Multiply<R>>("multiply")
+            .add::<LastBit<R>>("last_bit")
+            .add::<MultiplyByThree<R>>("multiply_by_three")
+            .add::<ModularDivideByThree<R>>("modular_divide_by_three")
+            .add::<Hamming<R>>("hamming")
+            .add::<ParitySkip<R>>("parity_skip")
+            .add::<Rol7<R>>("rol7");
+        crusher
+    }
+
+    /// Register a transform under a name.
+    pub fn add<T: Transform<R> + 'static>(&mut self, name: &'static str) -> &mut Crusher<R> {
+        self.register(name, |rand| analysis::Report::new(T::transform(rand)))
+    }
+
+    /// Register an arbitrary report-producing closure under a name.
+    ///
+    /// This is the escape hatch for composing transforms by hand, e.g. chaining two of them to
+    /// catch patterns only visible after double weakening.
+    pub fn register<F>(&mut self, name: &'static str, f: F) -> &mut Crusher<R>
+    where
+        F: Fn(R) -> analysis::Report + 'static,
+    {
+        self.transforms.push((name, Box::new(f)));
+        self
+    }
+
+    /// Run every registered transform against a clone of the generator.
+    pub fn crush(&self, rand: R) -> CrushReport {
+        CrushReport {
+            breakdown: self.transforms
+                .iter()
+                .map(|entry| (entry.0, entry.1(rand.clone()).get_score()))
+                .collect(),
+        }
+    }
+}
+
+impl<R: Random + Clone + 'static> Default for Crusher<R> {
+    fn default() -> Crusher<R> {
+        Crusher::new()
+    }
+}
+
+/// A per-transform breakdown of scores.
+pub struct CrushReport {
+    breakdown: Vec<(&'static str, analysis::Score)>,
+}
+
+impl CrushReport {
+    /// The score of each transform, in registration order.
+    pub fn breakdown(&self) -> &[(&'static str, analysis::Score)] {
+        &self.breakdown
+    }
+
+    /// Sum every transform's score into a single integer, matching the free `crush` function.
+    pub fn total(&self) -> u32 {
+        self.breakdown
+            .iter()
+            .map(|entry| entry.1.total() as u32)
+            .sum()
+    }
+}